@@ -130,14 +130,19 @@ fn bench_remove_unordered(c: &mut Criterion) {
             &size,
             |b, size| {
                 let graph = make_two_track_dag(*size);
-                b.iter(|| black_box(remove_all_unordered(&mut graph.clone())))
+                b.iter(|| {
+                    remove_all_unordered(&mut graph.clone());
+                    black_box(())
+                })
             },
         );
     }
     g.finish();
 }
 
-fn generate_rewrite() -> (PortGraph<i8, i8>, Rewrite<PortGraph<i8, i8>, i8, i8>) {
+type GeneratedRewrite = (PortGraph<i8, i8>, Rewrite<PortGraph<i8, i8>, i8, i8>);
+
+fn generate_rewrite() -> GeneratedRewrite {
     let mut g = PortGraph::<i8, i8>::with_capacity(3, 2);
 
     let n0 = g.add_node(0, 0, 2);
@@ -157,9 +162,10 @@ fn generate_rewrite() -> (PortGraph<i8, i8>, Rewrite<PortGraph<i8, i8>, i8, i8>)
     let p3 = g2.output(n3, 0).unwrap();
 
     let rewrite = Rewrite::new(
-        BoundedSubgraph::new([n1].into_iter().collect(), vec![p0], vec![p1]),
+        BoundedSubgraph::new([n1].into_iter().collect(), vec![Some(p0)], vec![Some(p1)]),
         OpenGraph::new(g2, vec![p2], vec![p3]),
-    );
+    )
+    .unwrap();
 
     (g, rewrite)
 }
@@ -0,0 +1,99 @@
+//! Weakly-connected components of a port graph, via union-find over the
+//! node set, ignoring port/edge direction.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::graph::{Graph, NodeIndex};
+
+struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(size: usize) -> Self {
+        Self {
+            parent: (0..size).collect(),
+            rank: vec![0; size],
+        }
+    }
+
+    fn find(&mut self, x: usize) -> usize {
+        if self.parent[x] != x {
+            self.parent[x] = self.find(self.parent[x]);
+        }
+        self.parent[x]
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (root_a, root_b) = (self.find(a), self.find(b));
+        if root_a == root_b {
+            return;
+        }
+        match self.rank[root_a].cmp(&self.rank[root_b]) {
+            Ordering::Less => self.parent[root_a] = root_b,
+            Ordering::Greater => self.parent[root_b] = root_a,
+            Ordering::Equal => {
+                self.parent[root_b] = root_a;
+                self.rank[root_a] += 1;
+            }
+        }
+    }
+}
+
+/// Compute the weakly-connected components of `graph`: two nodes end up in
+/// the same component whenever there is a path between them through links,
+/// followed in either direction.
+pub fn connected_components<'a, N: 'a, P: 'a, G>(graph: &G) -> Vec<Vec<NodeIndex>>
+where
+    G: Graph<'a, N, P>,
+{
+    let nodes: Vec<NodeIndex> = graph.nodes_iter().collect();
+    let index_of: HashMap<NodeIndex, usize> =
+        nodes.iter().enumerate().map(|(i, &n)| (n, i)).collect();
+
+    let mut sets = UnionFind::new(nodes.len());
+    for (i, &n) in nodes.iter().enumerate() {
+        for &port in graph.inputs(n).iter().chain(graph.outputs(n)) {
+            if let Some(neighbour) = graph.port_link(port).and_then(|p| graph.port_node(p)) {
+                if let Some(&j) = index_of.get(&neighbour) {
+                    sets.union(i, j);
+                }
+            }
+        }
+    }
+
+    let mut components: HashMap<usize, Vec<NodeIndex>> = HashMap::new();
+    for (i, &n) in nodes.iter().enumerate() {
+        let root = sets.find(i);
+        components.entry(root).or_default().push(n);
+    }
+    components.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{GraphMut, PortGraph};
+
+    #[test]
+    fn groups_nodes_linked_through_either_direction() {
+        let mut graph = PortGraph::<(), ()>::with_capacity(4, 2);
+        let a = graph.add_node((), 0, 1);
+        let b = graph.add_node((), 1, 0);
+        let c = graph.add_node((), 0, 0);
+        let d = graph.add_node((), 0, 0);
+        graph.link_nodes(a, 0, b, 0).unwrap();
+
+        let mut components = connected_components(&graph);
+        for component in &mut components {
+            component.sort_by_key(|n| n.index());
+        }
+        components.sort_by_key(|component| component[0].index());
+
+        let mut a_b = [a, b];
+        a_b.sort_by_key(|n| n.index());
+        assert_eq!(components, vec![a_b.to_vec(), vec![c], vec![d]]);
+    }
+}
@@ -0,0 +1,134 @@
+//! Shortest-path costs over a port graph via Dijkstra's algorithm, using a
+//! binary-heap frontier.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::ops::Add;
+
+use crate::graph::{Graph, NodeIndex, PortIndex};
+
+/// A node paired with its tentative cost, ordered so that `BinaryHeap` (a
+/// max-heap) pops the *smallest* cost first.
+struct Scored<W> {
+    cost: W,
+    node: NodeIndex,
+}
+
+impl<W: PartialEq> PartialEq for Scored<W> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+impl<W: PartialEq> Eq for Scored<W> {}
+impl<W: PartialOrd> PartialOrd for Scored<W> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl<W: PartialOrd> Ord for Scored<W> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Compute the shortest-path cost from `source` to every node reachable from
+/// it, following links forward through nodes' outputs.
+///
+/// `weight` is given the outgoing port and the (linked) incoming port of
+/// each edge it is asked to price, and must return a non-negative cost.
+pub fn dijkstra<'a, N: 'a, P: 'a, W, G, F>(graph: &G, source: NodeIndex, mut weight: F) -> HashMap<NodeIndex, W>
+where
+    G: Graph<'a, N, P>,
+    W: Copy + Default + PartialOrd + Add<Output = W>,
+    F: FnMut(PortIndex, PortIndex) -> W,
+{
+    let mut costs = HashMap::new();
+    let mut frontier = BinaryHeap::new();
+
+    costs.insert(source, W::default());
+    frontier.push(Scored {
+        cost: W::default(),
+        node: source,
+    });
+
+    while let Some(Scored { cost, node }) = frontier.pop() {
+        if costs.get(&node).is_some_and(|&best| cost > best) {
+            continue;
+        }
+
+        for &port in graph.outputs(node) {
+            let Some(linked) = graph.port_link(port) else {
+                continue;
+            };
+            let Some(neighbour) = graph.port_node(linked) else {
+                continue;
+            };
+
+            let next_cost = cost + weight(port, linked);
+            let is_better = costs
+                .get(&neighbour)
+                .is_none_or(|&existing| next_cost < existing);
+            if is_better {
+                costs.insert(neighbour, next_cost);
+                frontier.push(Scored {
+                    cost: next_cost,
+                    node: neighbour,
+                });
+            }
+        }
+    }
+
+    costs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{GraphMut, PortGraph};
+
+    /// a --1--> b --1--> d
+    /// a --4--> c --1--> d
+    ///
+    /// The direct-looking `a -> c -> d` path costs 5, so the shortest route
+    /// to `d` goes through `b` at cost 2.
+    #[test]
+    fn finds_shortest_costs_over_a_weighted_graph() {
+        let mut graph = PortGraph::<(), isize>::with_capacity(4, 4);
+        let a = graph.add_node((), 0, 2);
+        let b = graph.add_node((), 1, 1);
+        let c = graph.add_node((), 1, 1);
+        let d = graph.add_node((), 2, 0);
+        graph.link_nodes(a, 0, b, 0).unwrap();
+        graph.link_nodes(a, 1, c, 0).unwrap();
+        graph.link_nodes(b, 0, d, 0).unwrap();
+        graph.link_nodes(c, 0, d, 1).unwrap();
+
+        let weight = |port: PortIndex, _linked: PortIndex| -> isize {
+            let (node, offset) = (graph.port_node(port).unwrap(), graph.port_offset(port).unwrap());
+            match (node, offset) {
+                (n, 0) if n == a => 1,
+                (n, 1) if n == a => 4,
+                _ => 1,
+            }
+        };
+
+        let costs = dijkstra(&graph, a, weight);
+
+        assert_eq!(costs[&a], 0);
+        assert_eq!(costs[&b], 1);
+        assert_eq!(costs[&c], 4);
+        assert_eq!(costs[&d], 2);
+    }
+
+    #[test]
+    fn unreachable_nodes_are_absent() {
+        let mut graph = PortGraph::<(), isize>::with_capacity(2, 0);
+        let a = graph.add_node((), 0, 0);
+        let unreachable = graph.add_node((), 0, 0);
+
+        let costs = dijkstra(&graph, a, |_, _| 1isize);
+
+        assert_eq!(costs.len(), 1);
+        assert!(!costs.contains_key(&unreachable));
+    }
+}
@@ -0,0 +1,13 @@
+//! Algorithms that operate over any [`crate::graph::Graph`]/[`crate::graph::GraphMut`]
+//! implementation, rather than being tied to [`crate::graph::PortGraph`]
+//! directly.
+
+mod connected_components;
+mod dijkstra;
+mod toposort;
+mod transitive_reduction;
+
+pub use connected_components::connected_components;
+pub use dijkstra::dijkstra;
+pub use toposort::toposort;
+pub use transitive_reduction::transitive_reduction;
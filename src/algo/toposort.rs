@@ -0,0 +1,90 @@
+//! Topological ordering of acyclic port graphs, via Kahn's algorithm.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::graph::{Graph, NodeIndex};
+
+/// Produce a topological order of `graph`'s nodes: every node appears after
+/// every node with a link into one of its inputs.
+///
+/// Returns `Err` holding one node that sits on a cycle if `graph` is not
+/// acyclic.
+pub fn toposort<'a, N: 'a, P: 'a, G>(graph: &G) -> Result<Vec<NodeIndex>, NodeIndex>
+where
+    G: Graph<'a, N, P>,
+{
+    let nodes: Vec<NodeIndex> = graph.nodes_iter().collect();
+
+    let mut in_degree: HashMap<NodeIndex, usize> = nodes.iter().map(|&n| (n, 0)).collect();
+    for &n in &nodes {
+        for &port in graph.outputs(n) {
+            if let Some(successor) = graph.port_link(port).and_then(|p| graph.port_node(p)) {
+                *in_degree.entry(successor).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut queue: VecDeque<NodeIndex> = nodes
+        .iter()
+        .copied()
+        .filter(|n| in_degree[n] == 0)
+        .collect();
+    let mut order = Vec::with_capacity(nodes.len());
+
+    while let Some(n) = queue.pop_front() {
+        order.push(n);
+        for &port in graph.outputs(n) {
+            if let Some(successor) = graph.port_link(port).and_then(|p| graph.port_node(p)) {
+                let degree = in_degree.get_mut(&successor).unwrap();
+                *degree -= 1;
+                if *degree == 0 {
+                    queue.push_back(successor);
+                }
+            }
+        }
+    }
+
+    if order.len() == nodes.len() {
+        Ok(order)
+    } else {
+        let stuck = nodes
+            .into_iter()
+            .find(|n| in_degree[n] > 0)
+            .expect("order is shorter than nodes, so some node must still have nonzero in-degree");
+        Err(stuck)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{GraphMut, PortGraph};
+
+    #[test]
+    fn orders_a_dag_so_every_edge_points_forward() {
+        let mut graph = PortGraph::<(), ()>::with_capacity(3, 2);
+        let a = graph.add_node((), 0, 1);
+        let b = graph.add_node((), 1, 1);
+        let c = graph.add_node((), 1, 0);
+        graph.link_nodes(a, 0, b, 0).unwrap();
+        graph.link_nodes(b, 0, c, 0).unwrap();
+
+        let order = toposort(&graph).unwrap();
+
+        let position = |n: NodeIndex| order.iter().position(|&m| m == n).unwrap();
+        assert!(position(a) < position(b));
+        assert!(position(b) < position(c));
+    }
+
+    #[test]
+    fn reports_a_node_on_a_cycle() {
+        let mut graph = PortGraph::<(), ()>::with_capacity(2, 2);
+        let a = graph.add_node((), 1, 1);
+        let b = graph.add_node((), 1, 1);
+        graph.link_nodes(a, 0, b, 0).unwrap();
+        graph.link_nodes(b, 0, a, 0).unwrap();
+
+        let err = toposort(&graph).unwrap_err();
+        assert!(err == a || err == b);
+    }
+}
@@ -0,0 +1,372 @@
+//! Transitive reduction of acyclic port graphs: drop intermediate nodes that
+//! add no branching, while preserving exactly which designated "input" nodes
+//! can reach which designated "output" nodes.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::graph::{Graph, GraphMut, NodeIndex, PortGraph, PortIndex};
+
+/// Compute a smaller graph that keeps every node in `outputs` and preserves
+/// input-to-output reachability exactly, splicing out intermediate nodes
+/// that do not branch.
+///
+/// `graph` must be acyclic. A node is only ever spliced out (its
+/// predecessors rewired directly onward) when it is not itself an input or
+/// output node and is not "shared": shared means it has two or more
+/// successors whose reachable-output sets are actually distinct. A node
+/// consumed by several successors that all reach the exact same outputs
+/// isn't branching in any way that matters and is spliced out just like one
+/// with a single successor; collapsing a genuinely shared node, by
+/// contrast, would lose that branching structure.
+///
+/// Every port surviving in the output belongs to a retained node and keeps
+/// the weight of the original port it corresponds to (a retained node's own
+/// port, on whichever side of the edge it sits).
+pub fn transitive_reduction<N, P>(
+    graph: &PortGraph<N, P>,
+    inputs: &[NodeIndex],
+    outputs: &[NodeIndex],
+) -> PortGraph<N, P>
+where
+    N: Clone,
+    P: Clone,
+{
+    let nodes: Vec<NodeIndex> = graph.nodes_iter().collect();
+    let order = topological_order(graph, &nodes);
+
+    let successors = |n: NodeIndex| -> Vec<NodeIndex> {
+        let mut succ: Vec<NodeIndex> = graph
+            .outputs(n)
+            .iter()
+            .filter_map(|&port| graph.port_link(port))
+            .filter_map(|port| graph.port_node(port))
+            .collect();
+        succ.sort_by_key(|n| n.index());
+        succ.dedup();
+        succ
+    };
+
+    let input_set: HashSet<NodeIndex> = inputs.iter().copied().collect();
+    let output_set: HashSet<NodeIndex> = outputs.iter().copied().collect();
+
+    // The set of output nodes reachable from each node, computed in reverse
+    // topological order so that a node's successors are already known by
+    // the time it is processed.
+    let mut reach: HashMap<NodeIndex, HashSet<NodeIndex>> = HashMap::new();
+    for &n in order.iter().rev() {
+        let mut r = HashSet::new();
+        if output_set.contains(&n) {
+            r.insert(n);
+        }
+        for s in successors(n) {
+            if let Some(reach_s) = reach.get(&s) {
+                r.extend(reach_s.iter().copied());
+            }
+        }
+        reach.insert(n, r);
+    }
+
+    // A node is "shared" -- and must be retained -- only when it has at
+    // least two successors whose reachable-output sets actually differ. A
+    // node with several successors that all reach the exact same outputs
+    // isn't contributing any branching of its own and is as redundant as
+    // one with a single successor.
+    let has_distinct_successor_reach = |n: NodeIndex| -> bool {
+        let succ = successors(n);
+        let Some((first, rest)) = succ.split_first() else {
+            return false;
+        };
+        let first_reach = &reach[first];
+        rest.iter().any(|s| &reach[s] != first_reach)
+    };
+
+    let retain = |n: NodeIndex| -> bool {
+        input_set.contains(&n) || output_set.contains(&n) || has_distinct_successor_reach(n)
+    };
+
+    // Follow a chain of spliced-out nodes forward, starting from one of a
+    // retained node's own output ports, until a retained node is reached
+    // (returning the exact port it is reached through) or the chain
+    // dead-ends without contributing to any output. A spliced-out node may
+    // still have several successors (if their reach sets all agree), in
+    // which case any one of them leads to the same reachable outputs, so
+    // it's enough to follow its first linked output port.
+    //
+    // Resolving down to an actual port, rather than just a node, lets the
+    // reduced graph reuse both endpoints' real weights instead of
+    // synthesizing default ones.
+    let follow_to_port = |mut port: PortIndex| -> Option<PortIndex> {
+        loop {
+            let linked = graph.port_link(port)?;
+            let node = graph.port_node(linked).unwrap();
+            if retain(node) {
+                return Some(linked);
+            }
+            port = *graph.outputs(node).iter().find(|&&p| graph.port_link(p).is_some())?;
+        }
+    };
+
+    let retained: Vec<NodeIndex> = nodes.into_iter().filter(|&n| retain(n)).collect();
+
+    // Every retained node's own output port either dangles or resolves to
+    // exactly one (retained-node, retained-port) pair, so no two edges ever
+    // collide -- no need to dedup.
+    let edges: Vec<(PortIndex, PortIndex)> = retained
+        .iter()
+        .flat_map(|&n| graph.outputs(n).iter().copied())
+        .filter_map(|port| follow_to_port(port).map(|target| (port, target)))
+        .collect();
+
+    let mut new_outputs: HashMap<NodeIndex, Vec<P>> = HashMap::new();
+    let mut new_inputs: HashMap<NodeIndex, Vec<P>> = HashMap::new();
+    let mut new_edges: Vec<(NodeIndex, usize, NodeIndex, usize)> = Vec::new();
+    for (from_port, to_port) in edges {
+        let from_node = graph.port_node(from_port).unwrap();
+        let to_node = graph.port_node(to_port).unwrap();
+        let from_list = new_outputs.entry(from_node).or_default();
+        from_list.push(graph.port_weight(from_port).unwrap().clone());
+        let from_offset = from_list.len() - 1;
+        let to_list = new_inputs.entry(to_node).or_default();
+        to_list.push(graph.port_weight(to_port).unwrap().clone());
+        let to_offset = to_list.len() - 1;
+        new_edges.push((from_node, from_offset, to_node, to_offset));
+    }
+
+    let mut reduced = PortGraph::with_capacity(retained.len(), new_edges.len() * 2);
+    let mut node_map = HashMap::with_capacity(retained.len());
+    for &n in &retained {
+        let weight = graph.node_weight(n).unwrap().clone();
+        let inputs = new_inputs.remove(&n).unwrap_or_default();
+        let outputs = new_outputs.remove(&n).unwrap_or_default();
+        let new_node = reduced.add_node_with_ports(weight, inputs, outputs);
+        node_map.insert(n, new_node);
+    }
+
+    for (from, from_offset, to, to_offset) in new_edges {
+        reduced
+            .link_nodes(node_map[&from], from_offset, node_map[&to], to_offset)
+            .unwrap();
+    }
+
+    reduced
+}
+
+/// A topological order of `nodes`, computed via iterative postorder DFS
+/// along outgoing links and reversed. Assumes `graph` is acyclic.
+fn topological_order<N, P>(graph: &PortGraph<N, P>, nodes: &[NodeIndex]) -> Vec<NodeIndex> {
+    let mut visited = HashSet::new();
+    let mut order = Vec::with_capacity(nodes.len());
+
+    for &start in nodes {
+        if visited.contains(&start) {
+            continue;
+        }
+        let mut stack = vec![(start, false)];
+        while let Some((n, expanded)) = stack.pop() {
+            if expanded {
+                order.push(n);
+                continue;
+            }
+            if !visited.insert(n) {
+                continue;
+            }
+            stack.push((n, true));
+            for &port in graph.outputs(n) {
+                if let Some(successor) = graph.port_link(port).and_then(|p| graph.port_node(p)) {
+                    if !visited.contains(&successor) {
+                        stack.push((successor, false));
+                    }
+                }
+            }
+        }
+    }
+
+    order.reverse();
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::GraphMut;
+
+    fn reachable<N, P>(graph: &PortGraph<N, P>, from: NodeIndex, to: NodeIndex) -> bool {
+        if from == to {
+            return true;
+        }
+        let mut visited = HashSet::new();
+        let mut stack = vec![from];
+        while let Some(n) = stack.pop() {
+            if !visited.insert(n) {
+                continue;
+            }
+            for &port in graph.outputs(n) {
+                if let Some(successor) = graph.port_link(port).and_then(|p| graph.port_node(p)) {
+                    if successor == to {
+                        return true;
+                    }
+                    stack.push(successor);
+                }
+            }
+        }
+        false
+    }
+
+    /// A retained node's own boundary ports keep their original weight
+    /// across reduction, even when the chain between them is spliced out.
+    #[test]
+    fn preserves_port_weights_of_retained_nodes() {
+        let mut graph = PortGraph::<&str, i32>::with_capacity(3, 2);
+        let input = graph.add_node_with_ports("input", vec![], vec![42]);
+        let mid = graph.add_node_with_ports("mid", vec![0], vec![0]);
+        let output = graph.add_node_with_ports("output", vec![7], vec![]);
+        graph.link_nodes(input, 0, mid, 0).unwrap();
+        graph.link_nodes(mid, 0, output, 0).unwrap();
+
+        let reduced = transitive_reduction(&graph, &[input], &[output]);
+
+        assert_eq!(reduced.node_count(), 2);
+        let new_input = reduced
+            .nodes_iter()
+            .find(|&n| reduced.node_weight(n) == Some(&"input"))
+            .unwrap();
+        let new_output = reduced
+            .nodes_iter()
+            .find(|&n| reduced.node_weight(n) == Some(&"output"))
+            .unwrap();
+        assert_eq!(
+            reduced.port_weight(reduced.output(new_input, 0).unwrap()),
+            Some(&42)
+        );
+        assert_eq!(
+            reduced.port_weight(reduced.input(new_output, 0).unwrap()),
+            Some(&7)
+        );
+    }
+
+    /// A single-successor chain between an input and an output node is
+    /// fully spliced out, leaving the input linked straight to the output.
+    #[test]
+    fn chain_collapses_intermediate_nodes() {
+        let mut graph = PortGraph::<&str, ()>::with_capacity(3, 2);
+        let input = graph.add_node("input", 0, 1);
+        let mid = graph.add_node("mid", 1, 1);
+        let output = graph.add_node("output", 1, 0);
+        graph.link_nodes(input, 0, mid, 0).unwrap();
+        graph.link_nodes(mid, 0, output, 0).unwrap();
+
+        let reduced = transitive_reduction(&graph, &[input], &[output]);
+
+        assert_eq!(reduced.node_count(), 2);
+        let new_input = reduced
+            .nodes_iter()
+            .find(|&n| reduced.node_weight(n) == Some(&"input"))
+            .unwrap();
+        let new_output = reduced
+            .nodes_iter()
+            .find(|&n| reduced.node_weight(n) == Some(&"output"))
+            .unwrap();
+        assert!(reachable(&reduced, new_input, new_output));
+    }
+
+    /// A node with two successors that reach genuinely distinct output sets
+    /// is "shared" and must be retained: collapsing it would conflate which
+    /// input reaches which output.
+    #[test]
+    fn shared_node_with_distinct_reach_is_retained() {
+        let mut graph = PortGraph::<&str, ()>::with_capacity(4, 4);
+        let input = graph.add_node("input", 0, 1);
+        let mid = graph.add_node("mid", 1, 2);
+        let out1 = graph.add_node("out1", 1, 0);
+        let out2 = graph.add_node("out2", 1, 0);
+        graph.link_nodes(input, 0, mid, 0).unwrap();
+        graph.link_nodes(mid, 0, out1, 0).unwrap();
+        graph.link_nodes(mid, 1, out2, 0).unwrap();
+
+        let reduced = transitive_reduction(&graph, &[input], &[out1, out2]);
+
+        assert_eq!(reduced.node_count(), 4);
+        assert!(reduced
+            .nodes_iter()
+            .any(|n| reduced.node_weight(n) == Some(&"mid")));
+    }
+
+    /// A node with two successors that both reach the *same* output set is
+    /// not really branching, and is spliced out just like a single-successor
+    /// node would be -- this is the case the per-node `reach` sets exist to
+    /// distinguish from genuine sharing.
+    #[test]
+    fn node_with_identical_reach_successors_is_spliced() {
+        let mut graph = PortGraph::<&str, ()>::with_capacity(5, 5);
+        let input = graph.add_node("input", 0, 1);
+        let mid = graph.add_node("mid", 1, 2);
+        let s1 = graph.add_node("s1", 1, 1);
+        let s2 = graph.add_node("s2", 1, 1);
+        let output = graph.add_node("output", 2, 0);
+        graph.link_nodes(input, 0, mid, 0).unwrap();
+        graph.link_nodes(mid, 0, s1, 0).unwrap();
+        graph.link_nodes(mid, 1, s2, 0).unwrap();
+        graph.link_nodes(s1, 0, output, 0).unwrap();
+        graph.link_nodes(s2, 0, output, 1).unwrap();
+
+        let reduced = transitive_reduction(&graph, &[input], &[output]);
+
+        assert_eq!(reduced.node_count(), 2);
+        assert!(reduced
+            .nodes_iter()
+            .all(|n| reduced.node_weight(n) != Some(&"mid")));
+        let new_input = reduced
+            .nodes_iter()
+            .find(|&n| reduced.node_weight(n) == Some(&"input"))
+            .unwrap();
+        let new_output = reduced
+            .nodes_iter()
+            .find(|&n| reduced.node_weight(n) == Some(&"output"))
+            .unwrap();
+        assert!(reachable(&reduced, new_input, new_output));
+    }
+
+    /// A two-track DAG with a cross edge between the tracks (the shape the
+    /// benchmarks build before rewriting): reduction must preserve exactly
+    /// which input nodes can reach which output nodes.
+    #[test]
+    fn preserves_reachability_on_two_track_dag() {
+        let mut graph = PortGraph::<&str, ()>::with_capacity(7, 10);
+        let a0 = graph.add_node("a0", 0, 1);
+        let b0 = graph.add_node("b0", 0, 1);
+        let a1 = graph.add_node("a1", 1, 2);
+        let b1 = graph.add_node("b1", 2, 1);
+        let a2 = graph.add_node("a2", 1, 0);
+        let b2 = graph.add_node("b2", 1, 0);
+        graph.link_nodes(a0, 0, a1, 0).unwrap();
+        graph.link_nodes(b0, 0, b1, 0).unwrap();
+        graph.link_nodes(a1, 0, a2, 0).unwrap();
+        graph.link_nodes(a1, 1, b1, 1).unwrap();
+        graph.link_nodes(b1, 0, b2, 0).unwrap();
+
+        let inputs = [a0, b0];
+        let outputs = [a2, b2];
+        let reduced = transitive_reduction(&graph, &inputs, &outputs);
+
+        let weight_to_node = |reduced: &PortGraph<&str, ()>, weight: &str| {
+            reduced
+                .nodes_iter()
+                .find(|&n| reduced.node_weight(n) == Some(&weight))
+                .unwrap()
+        };
+
+        for &i in &inputs {
+            for &o in &outputs {
+                let before = reachable(&graph, i, o);
+                let i_name = *graph.node_weight(i).unwrap();
+                let o_name = *graph.node_weight(o).unwrap();
+                let after = reachable(
+                    &reduced,
+                    weight_to_node(&reduced, i_name),
+                    weight_to_node(&reduced, o_name),
+                );
+                assert_eq!(before, after, "reachability {i_name} -> {o_name} changed");
+            }
+        }
+    }
+}
@@ -0,0 +1,500 @@
+//! The core `PortGraph` data structure: a directed graph whose nodes expose
+//! an ordered list of input ports and an ordered list of output ports,
+//! rather than an unordered bag of edges.
+//!
+//! Ports (not nodes) are the units that get linked to one another, and the
+//! offset of a port within its node's input/output list is semantically
+//! meaningful -- it is preserved across operations such as [`Substitute`]
+//! rewriting. This mirrors the way e.g. quantum circuits or tensor networks
+//! are usually drawn: a gate has an ordered list of input and output wires.
+//!
+//! [`Substitute`]: crate::substitute::Substitute
+
+use thiserror::Error;
+
+/// Index of a node in a [`PortGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeIndex(usize);
+
+impl NodeIndex {
+    /// Create a new `NodeIndex` from a raw `usize`.
+    ///
+    /// This is mostly useful for algorithms that need to index auxiliary
+    /// arrays by node; constructing a `NodeIndex` that was not handed out by
+    /// a [`PortGraph`] is otherwise not meaningful.
+    pub fn new(index: usize) -> Self {
+        Self(index)
+    }
+
+    /// The raw index wrapped by this `NodeIndex`.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// Index of a port (either an input or an output) in a [`PortGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PortIndex(usize);
+
+impl PortIndex {
+    /// Create a new `PortIndex` from a raw `usize`.
+    pub fn new(index: usize) -> Self {
+        Self(index)
+    }
+
+    /// The raw index wrapped by this `PortIndex`.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// Whether a port is an input or an output of its node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Incoming,
+    Outgoing,
+}
+
+/// Errors that can occur while mutating a [`PortGraph`].
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum LinkError {
+    #[error("node {0:?} has no port at offset {1}")]
+    UnknownOffset(NodeIndex, usize),
+    #[error("port {0:?} does not exist")]
+    UnknownPort(PortIndex),
+    #[error("port {0:?} is already linked")]
+    PortLinked(PortIndex),
+}
+
+#[derive(Debug, Clone)]
+struct NodeData<N> {
+    weight: N,
+    inputs: Vec<PortIndex>,
+    outputs: Vec<PortIndex>,
+}
+
+#[derive(Debug, Clone)]
+struct PortData<P> {
+    weight: P,
+    node: NodeIndex,
+    offset: usize,
+    direction: Direction,
+    link: Option<PortIndex>,
+}
+
+/// A directed graph whose nodes carry a weight `N` and whose ports (ordered
+/// per-node, split between inputs and outputs) carry a weight `P`.
+///
+/// Removed nodes and ports leave a tombstone behind so that indices handed
+/// out before the removal are never silently reused for unrelated data; the
+/// freed slots are recycled by later `add_node*` calls instead.
+#[derive(Debug, Clone)]
+pub struct PortGraph<N, P> {
+    nodes: Vec<Option<NodeData<N>>>,
+    ports: Vec<Option<PortData<P>>>,
+    free_nodes: Vec<usize>,
+    free_ports: Vec<usize>,
+    node_count: usize,
+    port_count: usize,
+}
+
+impl<N, P> PortGraph<N, P> {
+    /// Create an empty graph, pre-allocating storage for `nodes` nodes and
+    /// `ports` ports.
+    pub fn with_capacity(nodes: usize, ports: usize) -> Self {
+        Self {
+            nodes: Vec::with_capacity(nodes),
+            ports: Vec::with_capacity(ports),
+            free_nodes: Vec::new(),
+            free_ports: Vec::new(),
+            node_count: 0,
+            port_count: 0,
+        }
+    }
+
+    fn alloc_port(&mut self, data: PortData<P>) -> PortIndex {
+        self.port_count += 1;
+        if let Some(index) = self.free_ports.pop() {
+            self.ports[index] = Some(data);
+            PortIndex(index)
+        } else {
+            self.ports.push(Some(data));
+            PortIndex(self.ports.len() - 1)
+        }
+    }
+
+    fn node_data(&self, node: NodeIndex) -> Option<&NodeData<N>> {
+        self.nodes.get(node.0)?.as_ref()
+    }
+
+    fn port_data(&self, port: PortIndex) -> Option<&PortData<P>> {
+        self.ports.get(port.0)?.as_ref()
+    }
+
+    /// Link two ports directly to one another, without going through their
+    /// owning nodes' offsets.
+    ///
+    /// Used by [`crate::substitute::Substitute`] to splice a replacement
+    /// graph's boundary ports onto the host graph's boundary ports, where
+    /// only the [`PortIndex`]es (and not a convenient node/offset pair) are
+    /// known.
+    pub fn link_ports(&mut self, from: PortIndex, to: PortIndex) -> Result<(), LinkError> {
+        if self.port_data(from).ok_or(LinkError::UnknownPort(from))?.link.is_some() {
+            return Err(LinkError::PortLinked(from));
+        }
+        if self.port_data(to).ok_or(LinkError::UnknownPort(to))?.link.is_some() {
+            return Err(LinkError::PortLinked(to));
+        }
+        self.ports[from.0].as_mut().unwrap().link = Some(to);
+        self.ports[to.0].as_mut().unwrap().link = Some(from);
+        Ok(())
+    }
+}
+
+/// Read-only access to a port graph.
+///
+/// Implemented by [`PortGraph`] directly; algorithms that only need to
+/// inspect a graph (e.g. [`crate::algo::toposort`]) should take `&impl Graph`
+/// rather than concrete `&PortGraph`, so they can later be reused for other
+/// backing stores.
+///
+/// The `'a` parameter lets generic callers (e.g. the `algo` module) name the
+/// node/port weight lifetimes they work with, independently of how long any
+/// one method call happens to borrow the graph.
+pub trait Graph<'a, N: 'a, P: 'a> {
+    /// Iterator over all live nodes, returned by [`Graph::nodes_iter`].
+    ///
+    /// Collected eagerly rather than borrowing from `self`, so that callers
+    /// can freely interleave a `nodes_iter()` call with a mutation in the
+    /// same expression (e.g. `graph.remove_node(graph.nodes_iter().next()...)`)
+    /// without fighting the borrow checker.
+    type NodesIter: Iterator<Item = NodeIndex>;
+
+    /// Number of live nodes in the graph.
+    fn node_count(&self) -> usize;
+
+    /// Number of live ports in the graph.
+    fn port_count(&self) -> usize;
+
+    /// Iterate over all live nodes, in index order.
+    fn nodes_iter(&self) -> Self::NodesIter;
+
+    /// The input ports of `node`, in order.
+    fn inputs(&self, node: NodeIndex) -> &[PortIndex];
+
+    /// The output ports of `node`, in order.
+    fn outputs(&self, node: NodeIndex) -> &[PortIndex];
+
+    /// The input port of `node` at `offset`, if it exists.
+    fn input(&self, node: NodeIndex, offset: usize) -> Option<PortIndex> {
+        self.inputs(node).get(offset).copied()
+    }
+
+    /// The output port of `node` at `offset`, if it exists.
+    fn output(&self, node: NodeIndex, offset: usize) -> Option<PortIndex> {
+        self.outputs(node).get(offset).copied()
+    }
+
+    /// The node that owns `port`.
+    fn port_node(&self, port: PortIndex) -> Option<NodeIndex>;
+
+    /// The offset of `port` within its node's input or output list.
+    fn port_offset(&self, port: PortIndex) -> Option<usize>;
+
+    /// Whether `port` is an input or an output.
+    fn port_direction(&self, port: PortIndex) -> Option<Direction>;
+
+    /// The port linked to `port`, if any.
+    fn port_link(&self, port: PortIndex) -> Option<PortIndex>;
+
+    /// The weight attached to `node`.
+    fn node_weight(&self, node: NodeIndex) -> Option<&N>;
+
+    /// The weight attached to `port`.
+    fn port_weight(&self, port: PortIndex) -> Option<&P>;
+
+    /// The nodes linked to the inputs and outputs of `node`, in port order.
+    fn neighbours(&self, node: NodeIndex) -> Vec<NodeIndex> {
+        self.inputs(node)
+            .iter()
+            .chain(self.outputs(node).iter())
+            .filter_map(|&port| self.port_link(port))
+            .filter_map(|port| self.port_node(port))
+            .collect()
+    }
+}
+
+/// Mutable access to a port graph: adding, removing and linking nodes.
+pub trait GraphMut<'a, N: 'a, P: 'a>: Graph<'a, N, P> {
+    /// Add a node weighted `weight`, with one input port per entry of
+    /// `inputs` and one output port per entry of `outputs`.
+    fn add_node_with_ports(&mut self, weight: N, inputs: Vec<P>, outputs: Vec<P>) -> NodeIndex;
+
+    /// Add a node weighted `weight`, with `incoming` inputs and `outgoing`
+    /// outputs, each port weighted with its type's [`Default`].
+    fn add_node(&mut self, weight: N, incoming: usize, outgoing: usize) -> NodeIndex
+    where
+        P: Default,
+    {
+        let inputs = (0..incoming).map(|_| P::default()).collect();
+        let outputs = (0..outgoing).map(|_| P::default()).collect();
+        self.add_node_with_ports(weight, inputs, outputs)
+    }
+
+    /// Remove `node` and all of its ports, unlinking any port that was
+    /// connected to one of them. Returns the node's weight, if it existed.
+    fn remove_node(&mut self, node: NodeIndex) -> Option<N>;
+
+    /// Link output port `from_offset` of `from` to input port `to_offset` of
+    /// `to`. Both ports must currently be unlinked.
+    fn link_nodes(
+        &mut self,
+        from: NodeIndex,
+        from_offset: usize,
+        to: NodeIndex,
+        to_offset: usize,
+    ) -> Result<(), LinkError>;
+
+    /// Remove the link (if any) attached to `port`, returning the port it
+    /// used to be linked to.
+    fn unlink_port(&mut self, port: PortIndex) -> Option<PortIndex>;
+}
+
+/// Iterator over the live nodes of a [`PortGraph`], returned by
+/// [`Graph::nodes_iter`].
+pub type NodesIter = std::vec::IntoIter<NodeIndex>;
+
+impl<'a, N: 'a, P: 'a> Graph<'a, N, P> for PortGraph<N, P> {
+    type NodesIter = NodesIter;
+
+    fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    fn port_count(&self) -> usize {
+        self.port_count
+    }
+
+    fn nodes_iter(&self) -> Self::NodesIter {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, data)| data.as_ref().map(|_| NodeIndex(index)))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn inputs(&self, node: NodeIndex) -> &[PortIndex] {
+        self.node_data(node).map(|n| n.inputs.as_slice()).unwrap_or(&[])
+    }
+
+    fn outputs(&self, node: NodeIndex) -> &[PortIndex] {
+        self.node_data(node).map(|n| n.outputs.as_slice()).unwrap_or(&[])
+    }
+
+    fn port_node(&self, port: PortIndex) -> Option<NodeIndex> {
+        self.port_data(port).map(|p| p.node)
+    }
+
+    fn port_offset(&self, port: PortIndex) -> Option<usize> {
+        self.port_data(port).map(|p| p.offset)
+    }
+
+    fn port_direction(&self, port: PortIndex) -> Option<Direction> {
+        self.port_data(port).map(|p| p.direction)
+    }
+
+    fn port_link(&self, port: PortIndex) -> Option<PortIndex> {
+        self.port_data(port).and_then(|p| p.link)
+    }
+
+    fn node_weight(&self, node: NodeIndex) -> Option<&N> {
+        self.node_data(node).map(|n| &n.weight)
+    }
+
+    fn port_weight(&self, port: PortIndex) -> Option<&P> {
+        self.port_data(port).map(|p| &p.weight)
+    }
+}
+
+impl<'a, N: 'a, P: 'a> GraphMut<'a, N, P> for PortGraph<N, P> {
+    fn add_node_with_ports(&mut self, weight: N, inputs: Vec<P>, outputs: Vec<P>) -> NodeIndex {
+        self.node_count += 1;
+        let node = if let Some(index) = self.free_nodes.pop() {
+            NodeIndex(index)
+        } else {
+            self.nodes.push(None);
+            NodeIndex(self.nodes.len() - 1)
+        };
+
+        let input_ports = inputs
+            .into_iter()
+            .enumerate()
+            .map(|(offset, weight)| {
+                self.alloc_port(PortData {
+                    weight,
+                    node,
+                    offset,
+                    direction: Direction::Incoming,
+                    link: None,
+                })
+            })
+            .collect();
+        let output_ports = outputs
+            .into_iter()
+            .enumerate()
+            .map(|(offset, weight)| {
+                self.alloc_port(PortData {
+                    weight,
+                    node,
+                    offset,
+                    direction: Direction::Outgoing,
+                    link: None,
+                })
+            })
+            .collect();
+
+        self.nodes[node.0] = Some(NodeData {
+            weight,
+            inputs: input_ports,
+            outputs: output_ports,
+        });
+        node
+    }
+
+    fn remove_node(&mut self, node: NodeIndex) -> Option<N> {
+        let data = self.nodes.get_mut(node.0)?.take()?;
+        self.node_count -= 1;
+        self.free_nodes.push(node.0);
+
+        for port in data.inputs.iter().chain(data.outputs.iter()) {
+            if let Some(Some(port_data)) = self.ports.get(port.0) {
+                if let Some(linked) = port_data.link {
+                    if let Some(Some(linked_data)) = self.ports.get_mut(linked.0) {
+                        linked_data.link = None;
+                    }
+                }
+            }
+            self.ports[port.0] = None;
+            self.port_count -= 1;
+            self.free_ports.push(port.0);
+        }
+
+        Some(data.weight)
+    }
+
+    fn link_nodes(
+        &mut self,
+        from: NodeIndex,
+        from_offset: usize,
+        to: NodeIndex,
+        to_offset: usize,
+    ) -> Result<(), LinkError> {
+        let from_port = *self
+            .node_data(from)
+            .and_then(|n| n.outputs.get(from_offset))
+            .ok_or(LinkError::UnknownOffset(from, from_offset))?;
+        let to_port = *self
+            .node_data(to)
+            .and_then(|n| n.inputs.get(to_offset))
+            .ok_or(LinkError::UnknownOffset(to, to_offset))?;
+
+        if self.port_data(from_port).unwrap().link.is_some() {
+            return Err(LinkError::PortLinked(from_port));
+        }
+        if self.port_data(to_port).unwrap().link.is_some() {
+            return Err(LinkError::PortLinked(to_port));
+        }
+
+        self.ports[from_port.0].as_mut().unwrap().link = Some(to_port);
+        self.ports[to_port.0].as_mut().unwrap().link = Some(from_port);
+        Ok(())
+    }
+
+    fn unlink_port(&mut self, port: PortIndex) -> Option<PortIndex> {
+        let linked = self.ports.get_mut(port.0)?.as_mut()?.link.take()?;
+        self.ports[linked.0].as_mut().unwrap().link = None;
+        Some(linked)
+    }
+}
+
+impl<N, P> Default for PortGraph<N, P> {
+    fn default() -> Self {
+        Self::with_capacity(0, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn link_nodes_connects_matching_ports() {
+        let mut graph = PortGraph::<&str, ()>::with_capacity(2, 2);
+        let a = graph.add_node("a", 0, 1);
+        let b = graph.add_node("b", 1, 0);
+
+        graph.link_nodes(a, 0, b, 0).unwrap();
+
+        let out_port = graph.output(a, 0).unwrap();
+        let in_port = graph.input(b, 0).unwrap();
+        assert_eq!(graph.port_link(out_port), Some(in_port));
+        assert_eq!(graph.port_link(in_port), Some(out_port));
+        assert_eq!(graph.port_node(in_port), Some(b));
+        assert_eq!(graph.port_direction(out_port), Some(Direction::Outgoing));
+    }
+
+    #[test]
+    fn link_nodes_rejects_unknown_offset_and_relink() {
+        let mut graph = PortGraph::<(), ()>::with_capacity(2, 2);
+        let a = graph.add_node((), 0, 1);
+        let b = graph.add_node((), 1, 0);
+
+        assert_eq!(
+            graph.link_nodes(a, 1, b, 0),
+            Err(LinkError::UnknownOffset(a, 1))
+        );
+
+        graph.link_nodes(a, 0, b, 0).unwrap();
+        assert_eq!(
+            graph.link_nodes(a, 0, b, 0),
+            Err(LinkError::PortLinked(graph.output(a, 0).unwrap()))
+        );
+    }
+
+    #[test]
+    fn link_ports_reports_unknown_port_not_already_linked() {
+        let mut graph = PortGraph::<(), ()>::with_capacity(1, 1);
+        let a = graph.add_node((), 0, 1);
+        let port = graph.output(a, 0).unwrap();
+        graph.remove_node(a);
+
+        assert_eq!(graph.link_ports(port, port), Err(LinkError::UnknownPort(port)));
+    }
+
+    #[test]
+    fn remove_node_unlinks_its_neighbours() {
+        let mut graph = PortGraph::<(), ()>::with_capacity(2, 2);
+        let a = graph.add_node((), 0, 1);
+        let b = graph.add_node((), 1, 0);
+        graph.link_nodes(a, 0, b, 0).unwrap();
+
+        assert_eq!(graph.remove_node(a), Some(()));
+        assert_eq!(graph.node_count(), 1);
+        let in_port = graph.input(b, 0).unwrap();
+        assert_eq!(graph.port_link(in_port), None);
+    }
+
+    #[test]
+    fn removed_node_slot_is_recycled_with_fresh_data() {
+        let mut graph = PortGraph::<&str, ()>::with_capacity(2, 0);
+        let a = graph.add_node("a", 0, 0);
+        assert_eq!(graph.remove_node(a), Some("a"));
+        assert_eq!(graph.node_weight(a), None);
+
+        let b = graph.add_node("b", 0, 0);
+
+        assert_eq!(graph.node_weight(b), Some(&"b"));
+        assert_eq!(graph.nodes_iter().collect::<Vec<_>>(), vec![b]);
+    }
+}
@@ -0,0 +1,210 @@
+//! Force-directed layout: assign 2D coordinates to the nodes of a
+//! [`PortGraph`] via a parallel Fruchterman-Reingold spring simulation, so
+//! that downstream tools can render the graph.
+//!
+//! Repulsion pushes every pair of nodes apart, and an attractive spring
+//! pulls each pair of linked nodes together; iterating this under a cooling
+//! schedule settles on a readable layout. To scale to graphs of the size
+//! `bench_make_portgraph` builds, each iteration's force computation is
+//! split into chunks and run across threads, reading a shared immutable
+//! snapshot of the current positions and writing into a separate
+//! next-position buffer that is swapped in once the iteration completes.
+
+use std::collections::HashMap;
+use std::thread;
+
+use crate::graph::{Graph, NodeIndex, PortGraph};
+
+/// Tunable parameters of the [`layout`] simulation.
+#[derive(Debug, Clone, Copy)]
+pub struct LayoutConfig {
+    /// Number of simulation steps to run.
+    pub iterations: usize,
+    /// Strength of the repulsive force between every pair of nodes.
+    pub repulsion: f32,
+    /// Strength of the attractive force along each edge.
+    pub spring: f32,
+    /// Number of worker threads to split each iteration's force
+    /// computation across.
+    pub threads: usize,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            iterations: 100,
+            repulsion: 1.0,
+            spring: 0.05,
+            threads: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        }
+    }
+}
+
+/// Compute a 2D layout of `graph`'s nodes under `config`, returning each
+/// node's final `(x, y)` position.
+pub fn layout<N, P>(graph: &PortGraph<N, P>, config: LayoutConfig) -> HashMap<NodeIndex, (f32, f32)> {
+    let nodes: Vec<NodeIndex> = graph.nodes_iter().collect();
+    let node_count = nodes.len();
+    if node_count == 0 {
+        return HashMap::new();
+    }
+
+    let index_of: HashMap<NodeIndex, usize> =
+        nodes.iter().enumerate().map(|(i, &node)| (node, i)).collect();
+    let adjacency: Vec<Vec<usize>> = nodes
+        .iter()
+        .map(|&node| {
+            graph
+                .outputs(node)
+                .iter()
+                .chain(graph.inputs(node))
+                .filter_map(|&port| graph.port_link(port))
+                .filter_map(|port| graph.port_node(port))
+                .filter_map(|neighbour| index_of.get(&neighbour).copied())
+                .collect()
+        })
+        .collect();
+
+    let mut positions: Vec<(f32, f32)> = (0..node_count).map(|i| initial_position(i as u64)).collect();
+    let mut next_positions = positions.clone();
+
+    let threads = config.threads.max(1).min(node_count);
+    let chunk_size = node_count.div_ceil(threads);
+
+    for iteration in 0..config.iterations {
+        let cooling = 1.0 - iteration as f32 / config.iterations as f32;
+
+        thread::scope(|scope| {
+            for (chunk_index, out_chunk) in next_positions.chunks_mut(chunk_size).enumerate() {
+                let start = chunk_index * chunk_size;
+                let positions = &positions;
+                let adjacency = &adjacency;
+                let config = &config;
+                scope.spawn(move || {
+                    for (offset, slot) in out_chunk.iter_mut().enumerate() {
+                        *slot = step_node(start + offset, positions, adjacency, config, cooling);
+                    }
+                });
+            }
+        });
+
+        std::mem::swap(&mut positions, &mut next_positions);
+    }
+
+    nodes.into_iter().zip(positions).collect()
+}
+
+/// The next position of node `i`: its current position displaced by the net
+/// of the repulsive force from every other node and the attractive spring
+/// force along each of its edges, scaled by `cooling`.
+fn step_node(
+    i: usize,
+    positions: &[(f32, f32)],
+    adjacency: &[Vec<usize>],
+    config: &LayoutConfig,
+    cooling: f32,
+) -> (f32, f32) {
+    let (xi, yi) = positions[i];
+    let mut fx = 0.0f32;
+    let mut fy = 0.0f32;
+
+    for (j, &(xj, yj)) in positions.iter().enumerate() {
+        if i == j {
+            continue;
+        }
+        let (dx, dy) = (xi - xj, yi - yj);
+        let distance = (dx * dx + dy * dy).sqrt().max(1e-3);
+        let force = config.repulsion / distance;
+        fx += force * dx / distance;
+        fy += force * dy / distance;
+    }
+
+    for &j in &adjacency[i] {
+        let (xj, yj) = positions[j];
+        let (dx, dy) = (xj - xi, yj - yi);
+        let distance = (dx * dx + dy * dy).sqrt().max(1e-3);
+        let force = config.spring * distance;
+        fx += force * dx / distance;
+        fy += force * dy / distance;
+    }
+
+    (xi + fx * cooling, yi + fy * cooling)
+}
+
+/// A pseudo-random starting position for node `seed`, spread over a
+/// `[-50, 50]` square so the simulation has something to untangle.
+///
+/// Uses a small xorshift generator rather than pulling in a `rand`
+/// dependency just to break the initial symmetry.
+fn initial_position(seed: u64) -> (f32, f32) {
+    let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15).wrapping_add(1);
+    let mut next_f32 = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state >> 11) as f32 / (1u64 << 53) as f32
+    };
+    (next_f32() * 100.0 - 50.0, next_f32() * 100.0 - 50.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::GraphMut;
+
+    fn distance((x0, y0): (f32, f32), (x1, y1): (f32, f32)) -> f32 {
+        ((x0 - x1).powi(2) + (y0 - y1).powi(2)).sqrt()
+    }
+
+    #[test]
+    fn empty_graph_has_empty_layout() {
+        let graph = PortGraph::<(), ()>::with_capacity(0, 0);
+        let positions = layout(&graph, LayoutConfig::default());
+        assert!(positions.is_empty());
+    }
+
+    #[test]
+    fn every_node_gets_a_finite_position() {
+        let mut graph = PortGraph::<(), ()>::with_capacity(4, 2);
+        let a = graph.add_node((), 0, 1);
+        let b = graph.add_node((), 1, 0);
+        let c = graph.add_node((), 0, 0);
+        graph.link_nodes(a, 0, b, 0).unwrap();
+
+        let positions = layout(&graph, LayoutConfig::default());
+
+        assert_eq!(positions.len(), 3);
+        for node in [a, b, c] {
+            let (x, y) = positions[&node];
+            assert!(x.is_finite() && y.is_finite());
+        }
+    }
+
+    /// A linked pair starts at their independent, unrelated xorshift
+    /// positions; the spring force should pull them closer together than
+    /// that starting distance, while two nodes with no edge between them
+    /// shouldn't be pulled together at all.
+    #[test]
+    fn spring_force_pulls_linked_nodes_closer() {
+        let mut graph = PortGraph::<(), ()>::with_capacity(3, 1);
+        let a = graph.add_node((), 0, 1);
+        let b = graph.add_node((), 1, 0);
+        let isolated = graph.add_node((), 0, 0);
+        graph.link_nodes(a, 0, b, 0).unwrap();
+
+        let initial = [initial_position(0), initial_position(1), initial_position(2)];
+        let config = LayoutConfig {
+            iterations: 200,
+            ..LayoutConfig::default()
+        };
+        let positions = layout(&graph, config);
+
+        let start_distance = distance(initial[0], initial[1]);
+        let end_distance = distance(positions[&a], positions[&b]);
+        assert!(
+            end_distance < start_distance,
+            "linked nodes should end up closer: {end_distance} vs {start_distance}"
+        );
+        let _ = isolated;
+    }
+}
@@ -0,0 +1,17 @@
+//! Data structures and algorithms for directed graphs whose nodes expose
+//! ordered, named ports rather than an unordered bag of edges.
+//!
+//! The central type is [`graph::PortGraph`]: a node's inputs and outputs are
+//! each an ordered list of ports, and the offset of a port within that list
+//! is preserved across operations such as [`substitute::Substitute`]
+//! rewriting. This is the representation needed for rewriting structures
+//! like quantum circuits or tensor networks, where port order is
+//! semantically meaningful.
+
+pub mod algo;
+pub mod graph;
+pub mod layout;
+pub mod matcher;
+pub mod substitute;
+
+pub use graph::{Graph, GraphMut, NodeIndex, PortGraph, PortIndex};
@@ -0,0 +1,316 @@
+//! Subgraph pattern matching: given a small pattern [`PortGraph`], find every
+//! embedding of it inside a host [`PortGraph`] as a ready-to-use
+//! [`BoundedSubgraph`], so that matches can be fed straight into
+//! [`Substitute::apply_rewrite`].
+//!
+//! [`Substitute::apply_rewrite`]: crate::substitute::Substitute::apply_rewrite
+
+use std::collections::{HashMap, HashSet};
+
+use crate::graph::{Graph, NodeIndex, PortGraph};
+use crate::substitute::BoundedSubgraph;
+
+/// Finds embeddings of a `pattern` graph inside a `host` graph using a
+/// VF2-style backtracking search.
+///
+/// A partial map is grown one node pair at a time. A candidate pair `(p, h)`
+/// is only extended into the map when `node_compatible(pattern_weight,
+/// host_weight)` holds, `h` has at least as many inputs and outputs as `p`,
+/// and the port structure is consistent: for every pattern neighbour of `p`
+/// that is already mapped, `h` must be linked to that neighbour's image
+/// through the exact same port offsets as `p` is linked to the neighbour.
+pub struct Matcher<'p, 'h, N, P, F> {
+    pattern: &'p PortGraph<N, P>,
+    host: &'h PortGraph<N, P>,
+    node_compatible: F,
+    pattern_nodes: Vec<NodeIndex>,
+}
+
+impl<'p, 'h, N, P, F> Matcher<'p, 'h, N, P, F>
+where
+    F: Fn(&N, &N) -> bool,
+{
+    /// Create a matcher for `pattern` within `host`, using `node_compatible`
+    /// to decide whether a pattern node's weight may match a host node's.
+    pub fn new(pattern: &'p PortGraph<N, P>, host: &'h PortGraph<N, P>, node_compatible: F) -> Self {
+        let pattern_nodes = pattern.nodes_iter().collect();
+        Self {
+            pattern,
+            host,
+            node_compatible,
+            pattern_nodes,
+        }
+    }
+
+    /// Find every embedding of the pattern in the host graph.
+    ///
+    /// Each embedding is returned as a [`BoundedSubgraph`] whose nodes are
+    /// the matched host nodes, and whose boundary ports are the host ports
+    /// reached by following the pattern's own dangling (unlinked) ports --
+    /// exactly the shape [`OpenGraph`] expects for a replacement. A match
+    /// whose dangling ports turn out to reach another matched node (an
+    /// internal host edge the pattern never constrained) is unsound and is
+    /// silently dropped rather than reported with a bogus boundary.
+    ///
+    /// [`OpenGraph`]: crate::substitute::OpenGraph
+    pub fn find_matches(&self) -> Vec<BoundedSubgraph> {
+        let mut mapping = HashMap::new();
+        let mut used_host = HashSet::new();
+        let mut matches = Vec::new();
+        self.search(0, &mut mapping, &mut used_host, &mut matches);
+        matches
+    }
+
+    fn search(
+        &self,
+        depth: usize,
+        mapping: &mut HashMap<NodeIndex, NodeIndex>,
+        used_host: &mut HashSet<NodeIndex>,
+        matches: &mut Vec<BoundedSubgraph>,
+    ) {
+        let Some(&p) = self.pattern_nodes.get(depth) else {
+            if let Some(embedding) = self.build_embedding(mapping) {
+                matches.push(embedding);
+            }
+            return;
+        };
+
+        for h in self.host.nodes_iter() {
+            if used_host.contains(&h) {
+                continue;
+            }
+            if !self.feasible(p, h, mapping) {
+                continue;
+            }
+            mapping.insert(p, h);
+            used_host.insert(h);
+            self.search(depth + 1, mapping, used_host, matches);
+            mapping.remove(&p);
+            used_host.remove(&h);
+        }
+    }
+
+    fn feasible(&self, p: NodeIndex, h: NodeIndex, mapping: &HashMap<NodeIndex, NodeIndex>) -> bool {
+        let p_weight = self.pattern.node_weight(p).unwrap();
+        let h_weight = self.host.node_weight(h).unwrap();
+        if !(self.node_compatible)(p_weight, h_weight) {
+            return false;
+        }
+        if self.host.inputs(h).len() < self.pattern.inputs(p).len() {
+            return false;
+        }
+        if self.host.outputs(h).len() < self.pattern.outputs(p).len() {
+            return false;
+        }
+
+        self.pattern
+            .inputs(p)
+            .iter()
+            .enumerate()
+            .all(|(offset, &port)| self.consistent_with_mapped(port, offset, h, true, mapping))
+            && self
+                .pattern
+                .outputs(p)
+                .iter()
+                .enumerate()
+                .all(|(offset, &port)| self.consistent_with_mapped(port, offset, h, false, mapping))
+    }
+
+    /// Check that, if the pattern neighbour reached through `port` is
+    /// already mapped, `h`'s port at the same `offset` (on the same side --
+    /// input when `is_input`, output otherwise) is linked to that
+    /// neighbour's image through the same offset.
+    fn consistent_with_mapped(
+        &self,
+        port: crate::graph::PortIndex,
+        offset: usize,
+        h: NodeIndex,
+        is_input: bool,
+        mapping: &HashMap<NodeIndex, NodeIndex>,
+    ) -> bool {
+        let Some(linked) = self.pattern.port_link(port) else {
+            return true;
+        };
+        let Some(&mapped_neighbour) = mapping.get(&self.pattern.port_node(linked).unwrap()) else {
+            return true;
+        };
+
+        let h_port = if is_input {
+            self.host.input(h, offset)
+        } else {
+            self.host.output(h, offset)
+        };
+        let Some(h_port) = h_port else {
+            return false;
+        };
+        let Some(h_linked) = self.host.port_link(h_port) else {
+            return false;
+        };
+
+        self.host.port_node(h_linked) == Some(mapped_neighbour)
+            && self.host.port_offset(h_linked) == self.pattern.port_offset(linked)
+    }
+
+    /// Build the embedding for a complete `mapping`, or `None` if the match
+    /// is unsound.
+    ///
+    /// A dangling pattern port only ever designates a genuine boundary when
+    /// the host-side link it follows reaches outside the matched node set --
+    /// if it instead reaches another matched node, the pattern simply never
+    /// constrained that pair and the match must be rejected, since treating
+    /// an internal host edge as a boundary port would hand
+    /// [`Substitute::apply_rewrite`] a port that belongs to a node about to
+    /// be removed.
+    ///
+    /// Each dangling port still contributes one slot to `inputs`/`outputs`
+    /// even when the host port underneath it is unlinked, since
+    /// [`BoundedSubgraph::inputs`]/[`outputs`] are paired positionally with
+    /// a replacement's boundary ports; that slot is `None`.
+    ///
+    /// [`Substitute::apply_rewrite`]: crate::substitute::Substitute::apply_rewrite
+    /// [`outputs`]: BoundedSubgraph::outputs
+    fn build_embedding(&self, mapping: &HashMap<NodeIndex, NodeIndex>) -> Option<BoundedSubgraph> {
+        let nodes: HashSet<NodeIndex> = mapping.values().copied().collect();
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+
+        for &p in &self.pattern_nodes {
+            let h = mapping[&p];
+            for (offset, &port) in self.pattern.inputs(p).iter().enumerate() {
+                if self.pattern.port_link(port).is_some() {
+                    continue;
+                }
+                let h_linked = self
+                    .host
+                    .input(h, offset)
+                    .and_then(|h_port| self.host.port_link(h_port));
+                if let Some(h_linked) = h_linked {
+                    if self.host.port_node(h_linked).is_some_and(|n| nodes.contains(&n)) {
+                        return None;
+                    }
+                }
+                inputs.push(h_linked);
+            }
+            for (offset, &port) in self.pattern.outputs(p).iter().enumerate() {
+                if self.pattern.port_link(port).is_some() {
+                    continue;
+                }
+                let h_linked = self
+                    .host
+                    .output(h, offset)
+                    .and_then(|h_port| self.host.port_link(h_port));
+                if let Some(h_linked) = h_linked {
+                    if self.host.port_node(h_linked).is_some_and(|n| nodes.contains(&n)) {
+                        return None;
+                    }
+                }
+                outputs.push(h_linked);
+            }
+        }
+
+        Some(BoundedSubgraph::new(nodes, inputs, outputs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::GraphMut;
+    use crate::substitute::{OpenGraph, Rewrite, Substitute};
+
+    /// A pattern of two disconnected, single-port nodes -- `a` with a
+    /// dangling output, `b` with a dangling input -- matched against a host
+    /// where the corresponding host nodes happen to be linked directly to
+    /// one another. The pattern never asserted that link, so it is internal
+    /// to the match, not a boundary, and must be rejected rather than
+    /// reported as a subgraph with a self-owned "boundary" port.
+    #[test]
+    fn rejects_match_with_internal_edge_misread_as_boundary() {
+        let mut pattern = PortGraph::<(), ()>::with_capacity(2, 2);
+        let a = pattern.add_node((), 0, 1);
+        let b = pattern.add_node((), 1, 0);
+
+        let mut host = PortGraph::<(), ()>::with_capacity(2, 2);
+        let ha = host.add_node((), 0, 1);
+        let hb = host.add_node((), 1, 0);
+        host.link_nodes(ha, 0, hb, 0).unwrap();
+
+        let matcher = Matcher::new(&pattern, &host, |_, _| true);
+        let matches = matcher.find_matches();
+
+        assert!(
+            matches.is_empty(),
+            "host edge between matched nodes must not be reported as a boundary: {:?}",
+            matches
+        );
+        let _ = (a, b);
+    }
+
+    /// A pattern node with two dangling inputs, matched against a host node
+    /// whose offset-0 input is unlinked and whose offset-1 input is fed by a
+    /// separate node. Both offsets must keep their own slot -- positionally
+    /// paired with a replacement's boundary ports -- rather than the
+    /// unlinked one being dropped and shifting offset 1 into its place.
+    #[test]
+    fn preserves_boundary_slots_for_unlinked_ports() {
+        let mut pattern = PortGraph::<(), ()>::with_capacity(1, 2);
+        pattern.add_node((), 2, 0);
+
+        let mut host = PortGraph::<(), ()>::with_capacity(2, 1);
+        let feeder = host.add_node((), 0, 1);
+        let target = host.add_node((), 2, 0);
+        host.link_nodes(feeder, 0, target, 1).unwrap();
+
+        let matcher = Matcher::new(&pattern, &host, |_, _| true);
+        let matches = matcher.find_matches();
+
+        assert_eq!(matches.len(), 1);
+        let inputs = matches[0].inputs();
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(inputs[0], None);
+        assert_eq!(inputs[1], host.input(target, 1).and_then(|p| host.port_link(p)));
+    }
+
+    /// A full match-then-rewrite round trip: matching a one-node pattern
+    /// inside a three-node chain and splicing in a two-node replacement
+    /// rewires the boundary exactly as the chain used to be connected.
+    #[test]
+    fn match_feeds_straight_into_apply_rewrite() {
+        let mut pattern = PortGraph::<&str, ()>::with_capacity(1, 2);
+        pattern.add_node("mid", 1, 1);
+
+        let mut host = PortGraph::<&str, ()>::with_capacity(3, 2);
+        let source = host.add_node("source", 0, 1);
+        let mid = host.add_node("mid", 1, 1);
+        let sink = host.add_node("sink", 1, 0);
+        host.link_nodes(source, 0, mid, 0).unwrap();
+        host.link_nodes(mid, 0, sink, 0).unwrap();
+
+        let matcher = Matcher::new(&pattern, &host, |p, h| p == h);
+        let mut matches = matcher.find_matches();
+        assert_eq!(matches.len(), 1);
+        let subgraph = matches.remove(0);
+        assert_eq!(subgraph.nodes(), &[mid].into_iter().collect());
+
+        let mut replacement_graph = PortGraph::<&str, ()>::with_capacity(1, 2);
+        let replacement_node = replacement_graph.add_node("replacement", 1, 1);
+        let replacement = OpenGraph::new(
+            replacement_graph.clone(),
+            vec![replacement_graph.input(replacement_node, 0).unwrap()],
+            vec![replacement_graph.output(replacement_node, 0).unwrap()],
+        );
+
+        let rewrite: Rewrite<PortGraph<&str, ()>, &str, ()> = Rewrite::new(subgraph, replacement).unwrap();
+        let host = host.apply_rewrite(rewrite);
+
+        assert_eq!(host.node_count(), 3);
+        let new_mid = host
+            .nodes_iter()
+            .find(|&n| host.node_weight(n) == Some(&"replacement"))
+            .unwrap();
+        let source_out = host.port_link(host.output(source, 0).unwrap()).unwrap();
+        assert_eq!(host.port_node(source_out), Some(new_mid));
+        let sink_in = host.port_link(host.input(sink, 0).unwrap()).unwrap();
+        assert_eq!(host.port_node(sink_in), Some(new_mid));
+    }
+}
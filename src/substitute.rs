@@ -0,0 +1,476 @@
+//! Rewriting a [`PortGraph`] by cutting out a subgraph and splicing in a
+//! replacement, reconnecting the replacement's boundary to wherever the
+//! removed nodes used to connect.
+
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+
+use thiserror::Error;
+
+use crate::graph::{Direction, Graph, GraphMut, NodeIndex, PortGraph, PortIndex};
+
+/// Errors that can occur while constructing a [`Rewrite`].
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RewriteError {
+    #[error("subgraph has {subgraph} boundary input(s) but replacement has {replacement}")]
+    InputCountMismatch { subgraph: usize, replacement: usize },
+    #[error("subgraph has {subgraph} boundary output(s) but replacement has {replacement}")]
+    OutputCountMismatch { subgraph: usize, replacement: usize },
+}
+
+/// A region of a host graph targeted for replacement: the set of nodes to
+/// remove, together with the host-side ports left dangling once they are
+/// gone.
+///
+/// `inputs` are the outgoing host ports that used to feed into the region;
+/// `outputs` are the incoming host ports that the region used to feed into.
+/// Both lists stay put in the host graph -- only the ports that the removed
+/// nodes themselves owned are gone along with them.
+///
+/// Each slot lines up positionally with a replacement's `OpenGraph::inputs`/
+/// `outputs`, so a slot is `None` rather than omitted when the boundary port
+/// it corresponds to simply wasn't connected to anything -- dropping the
+/// slot would shift every later one onto the wrong replacement port.
+#[derive(Debug, Clone)]
+pub struct BoundedSubgraph {
+    nodes: HashSet<NodeIndex>,
+    inputs: Vec<Option<PortIndex>>,
+    outputs: Vec<Option<PortIndex>>,
+}
+
+impl BoundedSubgraph {
+    /// Create a new bounded subgraph from its nodes and boundary ports.
+    pub fn new(
+        nodes: HashSet<NodeIndex>,
+        inputs: Vec<Option<PortIndex>>,
+        outputs: Vec<Option<PortIndex>>,
+    ) -> Self {
+        Self {
+            nodes,
+            inputs,
+            outputs,
+        }
+    }
+
+    /// The nodes that make up this subgraph.
+    pub fn nodes(&self) -> &HashSet<NodeIndex> {
+        &self.nodes
+    }
+
+    /// The host ports that feed into this subgraph, in matching order with
+    /// a replacement's [`OpenGraph::inputs`]. A `None` slot means that
+    /// boundary position had nothing connected to it in the host.
+    pub fn inputs(&self) -> &[Option<PortIndex>] {
+        &self.inputs
+    }
+
+    /// The host ports this subgraph feeds into, in matching order with a
+    /// replacement's [`OpenGraph::outputs`]. A `None` slot means that
+    /// boundary position had nothing connected to it in the host.
+    pub fn outputs(&self) -> &[Option<PortIndex>] {
+        &self.outputs
+    }
+}
+
+/// A standalone graph with a designated list of "dangling" input and output
+/// ports, used as the replacement half of a [`Rewrite`].
+#[derive(Debug, Clone)]
+pub struct OpenGraph<N, P> {
+    graph: PortGraph<N, P>,
+    inputs: Vec<PortIndex>,
+    outputs: Vec<PortIndex>,
+}
+
+impl<N, P> OpenGraph<N, P> {
+    /// Create a new open graph from its underlying graph and boundary ports.
+    pub fn new(graph: PortGraph<N, P>, inputs: Vec<PortIndex>, outputs: Vec<PortIndex>) -> Self {
+        Self {
+            graph,
+            inputs,
+            outputs,
+        }
+    }
+}
+
+/// A single rewrite: replace [`BoundedSubgraph`] of a host graph of type `G`
+/// with the [`OpenGraph`] `replacement`, connecting `replacement`'s boundary
+/// ports to the host ports left behind by the removed subgraph.
+///
+/// `G` is the type of host graph this rewrite is meant to be applied to; it
+/// does not otherwise appear in the rewrite's data, but pins down which
+/// [`Substitute`] impl `apply_rewrite` will use.
+#[derive(Debug)]
+pub struct Rewrite<G, N, P> {
+    subgraph: BoundedSubgraph,
+    replacement: OpenGraph<N, P>,
+    _host: PhantomData<fn() -> G>,
+}
+
+impl<G, N, P> Rewrite<G, N, P> {
+    /// Create a new rewrite from the subgraph to remove and its replacement.
+    ///
+    /// `subgraph.inputs()`/`outputs()` and `replacement`'s inputs/outputs
+    /// must have the same lengths, since they are wired together pairwise;
+    /// returns `Err` rather than building a `Rewrite` that would silently
+    /// leave boundary ports dangling at apply time.
+    pub fn new(subgraph: BoundedSubgraph, replacement: OpenGraph<N, P>) -> Result<Self, RewriteError> {
+        if subgraph.inputs.len() != replacement.inputs.len() {
+            return Err(RewriteError::InputCountMismatch {
+                subgraph: subgraph.inputs.len(),
+                replacement: replacement.inputs.len(),
+            });
+        }
+        if subgraph.outputs.len() != replacement.outputs.len() {
+            return Err(RewriteError::OutputCountMismatch {
+                subgraph: subgraph.outputs.len(),
+                replacement: replacement.outputs.len(),
+            });
+        }
+        Ok(Self {
+            subgraph,
+            replacement,
+            _host: PhantomData,
+        })
+    }
+}
+
+impl<G, N: Clone, P: Clone> Clone for Rewrite<G, N, P> {
+    fn clone(&self) -> Self {
+        Self {
+            subgraph: self.subgraph.clone(),
+            replacement: self.replacement.clone(),
+            _host: PhantomData,
+        }
+    }
+}
+
+/// A batch of [`Rewrite`]s queued up to be applied to the same host graph in
+/// a single pass.
+///
+/// Rewrites that were found by matching against a slightly stale host (e.g.
+/// several matches of the same pattern) routinely target overlapping
+/// regions; [`Substitute::apply_rewrites`] applies a maximal subset that
+/// don't, and hands the rest back unapplied.
+#[derive(Debug)]
+pub struct RewriteSet<G, N, P> {
+    rewrites: Vec<Rewrite<G, N, P>>,
+}
+
+impl<G, N, P> RewriteSet<G, N, P> {
+    /// Queue up `rewrites` to be applied together.
+    pub fn new(rewrites: Vec<Rewrite<G, N, P>>) -> Self {
+        Self { rewrites }
+    }
+
+    /// Split the queued rewrites into a maximal independent batch (in
+    /// first-seen order, each claiming its subgraph's nodes, boundary ports,
+    /// and the nodes that own those boundary ports) and the rewrites that
+    /// conflicted with an earlier one in the batch.
+    ///
+    /// `host` is consulted to find the node behind each boundary port: a
+    /// boundary port survives its own rewrite's removal, but it is still
+    /// only safe to keep relying on it while no *other* accepted rewrite
+    /// removes the node that owns it.
+    #[allow(clippy::type_complexity)]
+    fn partition_by_conflict<'a>(self, host: &G) -> (Vec<Rewrite<G, N, P>>, Vec<Rewrite<G, N, P>>)
+    where
+        N: 'a,
+        P: 'a,
+        G: Graph<'a, N, P>,
+    {
+        let mut claimed_nodes = HashSet::new();
+        let mut claimed_ports = HashSet::new();
+        let mut accepted = Vec::new();
+        let mut rejected = Vec::new();
+
+        for rewrite in self.rewrites {
+            let boundary: Vec<PortIndex> = rewrite
+                .subgraph
+                .inputs()
+                .iter()
+                .chain(rewrite.subgraph.outputs())
+                .filter_map(|&port| port)
+                .collect();
+            let boundary_owners: Vec<NodeIndex> = boundary
+                .iter()
+                .filter_map(|&port| host.port_node(port))
+                .collect();
+
+            let conflicts = rewrite
+                .subgraph
+                .nodes()
+                .iter()
+                .any(|node| claimed_nodes.contains(node))
+                || boundary.iter().any(|port| claimed_ports.contains(port))
+                || boundary_owners.iter().any(|node| claimed_nodes.contains(node));
+
+            if conflicts {
+                rejected.push(rewrite);
+                continue;
+            }
+
+            claimed_nodes.extend(rewrite.subgraph.nodes().iter().copied());
+            claimed_nodes.extend(boundary_owners);
+            claimed_ports.extend(boundary);
+            accepted.push(rewrite);
+        }
+
+        (accepted, rejected)
+    }
+}
+
+/// Graphs that can have a [`Rewrite`] applied to them.
+pub trait Substitute<N, P>: Sized {
+    /// Remove `rewrite`'s subgraph and splice in its replacement, rewiring
+    /// the replacement's boundary ports to the host ports the subgraph used
+    /// to be connected through.
+    fn apply_rewrite(self, rewrite: Rewrite<Self, N, P>) -> Self;
+
+    /// Apply a maximal subset of `rewrites` whose subgraphs claim disjoint
+    /// nodes and boundary ports (including the nodes that own those
+    /// boundary ports), in a single pass over `self`, instead of cloning and
+    /// re-matching between every individual [`apply_rewrite`].
+    ///
+    /// Returns the updated graph together with the rewrites that conflicted
+    /// with an earlier one in the batch and were not applied, so the caller
+    /// can re-match against the new graph and retry them.
+    ///
+    /// [`apply_rewrite`]: Substitute::apply_rewrite
+    fn apply_rewrites<'a>(
+        self,
+        rewrites: RewriteSet<Self, N, P>,
+    ) -> (Self, Vec<Rewrite<Self, N, P>>)
+    where
+        N: 'a,
+        P: 'a,
+        Self: Graph<'a, N, P>,
+    {
+        let (accepted, rejected) = rewrites.partition_by_conflict(&self);
+        let host = accepted
+            .into_iter()
+            .fold(self, |host, rewrite| host.apply_rewrite(rewrite));
+        (host, rejected)
+    }
+}
+
+impl<N: Clone, P: Clone> Substitute<N, P> for PortGraph<N, P> {
+    fn apply_rewrite(self, rewrite: Rewrite<Self, N, P>) -> Self {
+        let mut host = self;
+        let Rewrite {
+            subgraph,
+            replacement,
+            ..
+        } = rewrite;
+
+        for node in &subgraph.nodes {
+            host.remove_node(*node);
+        }
+
+        // Copy every node of the replacement graph into the host, keeping
+        // track of where each of its nodes and ports ended up.
+        let mut node_map = HashMap::new();
+        let mut port_map = HashMap::new();
+        for node in replacement.graph.nodes_iter() {
+            let weight = replacement.graph.node_weight(node).unwrap().clone();
+            let inputs: Vec<P> = replacement
+                .graph
+                .inputs(node)
+                .iter()
+                .map(|&port| replacement.graph.port_weight(port).unwrap().clone())
+                .collect();
+            let outputs: Vec<P> = replacement
+                .graph
+                .outputs(node)
+                .iter()
+                .map(|&port| replacement.graph.port_weight(port).unwrap().clone())
+                .collect();
+
+            let new_node = host.add_node_with_ports(weight, inputs, outputs);
+            node_map.insert(node, new_node);
+            for (offset, &port) in replacement.graph.inputs(node).iter().enumerate() {
+                port_map.insert(port, host.input(new_node, offset).unwrap());
+            }
+            for (offset, &port) in replacement.graph.outputs(node).iter().enumerate() {
+                port_map.insert(port, host.output(new_node, offset).unwrap());
+            }
+        }
+
+        // Re-create the replacement's internal links between copied ports.
+        let mut relinked = HashSet::new();
+        for (&old_port, &new_port) in &port_map {
+            if relinked.contains(&old_port) {
+                continue;
+            }
+            if let Some(old_linked) = replacement.graph.port_link(old_port) {
+                relinked.insert(old_port);
+                relinked.insert(old_linked);
+                let new_linked = port_map[&old_linked];
+                match replacement.graph.port_direction(old_port).unwrap() {
+                    Direction::Outgoing => host.link_ports(new_port, new_linked).unwrap(),
+                    Direction::Incoming => host.link_ports(new_linked, new_port).unwrap(),
+                };
+            }
+        }
+
+        // Wire the replacement's boundary onto the host ports the removed
+        // subgraph used to be connected through. A `None` slot means that
+        // boundary position had nothing connected to it in the host, so the
+        // matching replacement port is simply left dangling.
+        for (&host_port, &repl_port) in subgraph.inputs.iter().zip(&replacement.inputs) {
+            if let Some(host_port) = host_port {
+                host.link_ports(host_port, port_map[&repl_port]).unwrap();
+            }
+        }
+        for (&repl_port, &host_port) in replacement.outputs.iter().zip(&subgraph.outputs) {
+            if let Some(host_port) = host_port {
+                host.link_ports(port_map[&repl_port], host_port).unwrap();
+            }
+        }
+
+        host
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::GraphMut;
+
+    /// Build a `source -> mid -> sink` chain and a `Rewrite` that replaces
+    /// `mid` with a single fresh node, returning the host and the rewrite.
+    #[allow(clippy::type_complexity)]
+    fn make_chain_rewrite() -> (
+        PortGraph<&'static str, ()>,
+        NodeIndex,
+        NodeIndex,
+        Rewrite<PortGraph<&'static str, ()>, &'static str, ()>,
+    ) {
+        let mut host = PortGraph::<&str, ()>::with_capacity(3, 2);
+        let source = host.add_node("source", 0, 1);
+        let mid = host.add_node("mid", 1, 1);
+        let sink = host.add_node("sink", 1, 0);
+        host.link_nodes(source, 0, mid, 0).unwrap();
+        host.link_nodes(mid, 0, sink, 0).unwrap();
+
+        let host_in = host.output(source, 0).unwrap();
+        let host_out = host.input(sink, 0).unwrap();
+        let subgraph = BoundedSubgraph::new([mid].into_iter().collect(), vec![Some(host_in)], vec![Some(host_out)]);
+
+        let mut replacement_graph = PortGraph::<&str, ()>::with_capacity(1, 2);
+        let replacement_node = replacement_graph.add_node("replacement", 1, 1);
+        let replacement = OpenGraph::new(
+            replacement_graph.clone(),
+            vec![replacement_graph.input(replacement_node, 0).unwrap()],
+            vec![replacement_graph.output(replacement_node, 0).unwrap()],
+        );
+
+        let rewrite = Rewrite::new(subgraph, replacement).unwrap();
+        (host, source, sink, rewrite)
+    }
+
+    #[test]
+    fn apply_rewrites_applies_a_disjoint_batch() {
+        let (mut host, source1, _sink1, rewrite1) = make_chain_rewrite();
+        let (other, source2, sink2, rewrite2) = make_chain_rewrite();
+
+        // Graft `other`'s chain onto `host` so the two rewrites target
+        // disjoint nodes of the same graph.
+        let mut node_map = HashMap::new();
+        for node in other.nodes_iter() {
+            let weight = *other.node_weight(node).unwrap();
+            let inputs = other.inputs(node).len();
+            let outputs = other.outputs(node).len();
+            node_map.insert(node, host.add_node(weight, inputs, outputs));
+        }
+        for node in other.nodes_iter() {
+            for (offset, &port) in other.outputs(node).iter().enumerate() {
+                if let Some(linked) = other.port_link(port) {
+                    let to = other.port_node(linked).unwrap();
+                    let to_offset = other.port_offset(linked).unwrap();
+                    host.link_nodes(node_map[&node], offset, node_map[&to], to_offset)
+                        .unwrap();
+                }
+            }
+        }
+        let source2 = node_map[&source2];
+        let sink2 = node_map[&sink2];
+        let rewrite2 = Rewrite::new(
+            BoundedSubgraph::new(
+                rewrite2.subgraph.nodes().iter().map(|n| node_map[n]).collect(),
+                vec![Some(host.output(source2, 0).unwrap())],
+                vec![Some(host.input(sink2, 0).unwrap())],
+            ),
+            rewrite2.replacement,
+        )
+        .unwrap();
+
+        let rewrites = RewriteSet::new(vec![rewrite1, rewrite2]);
+        let (host, rejected) = host.apply_rewrites(rewrites);
+
+        assert!(rejected.is_empty());
+        assert_eq!(host.node_count(), 6);
+        let source1_out = host.port_link(host.output(source1, 0).unwrap()).unwrap();
+        assert_eq!(
+            host.node_weight(host.port_node(source1_out).unwrap()),
+            Some(&"replacement")
+        );
+        let source2_out = host.port_link(host.output(source2, 0).unwrap()).unwrap();
+        assert_eq!(
+            host.node_weight(host.port_node(source2_out).unwrap()),
+            Some(&"replacement")
+        );
+    }
+
+    #[test]
+    fn apply_rewrites_rejects_a_rewrite_overlapping_an_earlier_one() {
+        let (host, _source, _sink, rewrite1) = make_chain_rewrite();
+
+        // A second rewrite targeting the exact same subgraph conflicts on
+        // both its node set and its boundary ports.
+        let rewrite2 = rewrite1.clone();
+
+        let rewrites = RewriteSet::new(vec![rewrite1, rewrite2]);
+        let (host, rejected) = host.apply_rewrites(rewrites);
+
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(host.node_count(), 3);
+    }
+
+    /// A replacement with fewer boundary outputs than the subgraph it would
+    /// replace can't be wired up without leaving a host port dangling with
+    /// no diagnostic, so `Rewrite::new` must reject it up front.
+    #[test]
+    fn rewrite_new_rejects_boundary_length_mismatch() {
+        let mut host = PortGraph::<(), ()>::with_capacity(3, 3);
+        let source = host.add_node((), 0, 1);
+        let mid = host.add_node((), 1, 2);
+        let sink1 = host.add_node((), 1, 0);
+        let sink2 = host.add_node((), 1, 0);
+        host.link_nodes(source, 0, mid, 0).unwrap();
+        host.link_nodes(mid, 0, sink1, 0).unwrap();
+        host.link_nodes(mid, 1, sink2, 0).unwrap();
+
+        let subgraph = BoundedSubgraph::new(
+            [mid].into_iter().collect(),
+            vec![Some(host.output(source, 0).unwrap())],
+            vec![Some(host.input(sink1, 0).unwrap()), Some(host.input(sink2, 0).unwrap())],
+        );
+
+        let mut replacement_graph = PortGraph::<(), ()>::with_capacity(1, 1);
+        let replacement_node = replacement_graph.add_node((), 1, 1);
+        let replacement = OpenGraph::new(
+            replacement_graph.clone(),
+            vec![replacement_graph.input(replacement_node, 0).unwrap()],
+            vec![replacement_graph.output(replacement_node, 0).unwrap()],
+        );
+
+        let err = Rewrite::<PortGraph<(), ()>, (), ()>::new(subgraph, replacement).unwrap_err();
+        assert_eq!(
+            err,
+            RewriteError::OutputCountMismatch {
+                subgraph: 2,
+                replacement: 1,
+            }
+        );
+    }
+}